@@ -0,0 +1,182 @@
+//! Parses Unix `ar` archives (the format used for `.a` static libraries) well enough
+//! to scan each object-file member for embedded audit data.
+//!
+//! `staticlib` artifacts never go through a final link step, so `cargo auditable`
+//! embeds the audit data into one of the archive's member object files instead of
+//! into the archive as a whole. Both the thin (BSD `ar -s`-less) and fat (with a
+//! symbol table) variants share the same member layout, so no special-casing is
+//! needed beyond skipping the symbol table and GNU extended-name-table members.
+
+use crate::{Compression, Error};
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const HEADER_LEN: usize = 60;
+
+/// Returns `true` if `data` starts with the `ar` archive magic.
+pub fn is_ar_archive(data: &[u8]) -> bool {
+    data.starts_with(AR_MAGIC)
+}
+
+/// Scans every object member of the archive and returns the versioned dependency section
+/// contents from the first one that has it.
+pub fn section_with_name(data: &[u8]) -> Result<(Compression, &[u8]), Error> {
+    let mut offset = AR_MAGIC.len();
+    while offset + HEADER_LEN <= data.len() {
+        let header = &data[offset..offset + HEADER_LEN];
+        let name = &header[0..16];
+        let size: usize = std::str::from_utf8(&header[48..58])
+            .map_err(|_| Error::MalformedFile)?
+            .trim()
+            .parse()
+            .map_err(|_| Error::MalformedFile)?;
+
+        let member_start = offset + HEADER_LEN;
+        let member_end = member_start
+            .checked_add(size)
+            .ok_or(Error::UnexpectedEof)?;
+        let member = data.get(member_start..member_end).ok_or(Error::UnexpectedEof)?;
+
+        // "/" is the symbol table and "//" is the GNU extended name table -
+        // neither is an object file, so don't bother trying to parse them.
+        if !(name.starts_with(b"/ ") || name.starts_with(b"// ")) {
+            if let Ok(section) = crate::raw_auditable_data(member) {
+                return Ok(section);
+            }
+        }
+
+        // Archive members are padded to an even length with a trailing newline.
+        offset = member_end + (size % 2);
+    }
+    Err(Error::NoAuditData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a 60-byte `ar` member header, mirroring `cargo_auditable::ar_writer`'s
+    /// writer side closely enough to round-trip through this module's reader.
+    fn member_header(name: &[u8], size: usize) -> [u8; HEADER_LEN] {
+        let mut header = [b' '; HEADER_LEN];
+        header[0..name.len()].copy_from_slice(name);
+        header[16..17].copy_from_slice(b"0"); // mtime
+        header[28..29].copy_from_slice(b"0"); // uid
+        header[34..35].copy_from_slice(b"0"); // gid
+        header[40..46].copy_from_slice(b"100644"); // mode
+        let size_str = size.to_string();
+        header[48..48 + size_str.len()].copy_from_slice(size_str.as_bytes());
+        header[58] = b'`';
+        header[59] = b'\n';
+        header
+    }
+
+    /// Builds a minimal ELF64 object with one `.dep-v0` section holding `payload`.
+    fn build_elf_with_dep_section(payload: &[u8]) -> Vec<u8> {
+        let ehdr_len = 64usize;
+        let shdr_len = 64usize;
+        let payload_offset = ehdr_len;
+        let shstrtab = b"\0.dep-v0\0.shstrtab\0";
+        let shstrtab_offset = payload_offset + payload.len();
+        let shoff = shstrtab_offset + shstrtab.len();
+
+        let mut out = Vec::new();
+        // e_ident
+        out.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+        out.extend_from_slice(&[0u8; 8]);
+        out.extend_from_slice(&1u16.to_le_bytes()); // e_type (ET_REL)
+        out.extend_from_slice(&0x3eu16.to_le_bytes()); // e_machine (EM_X86_64)
+        out.extend_from_slice(&1u32.to_le_bytes()); // e_version
+        out.extend_from_slice(&0u64.to_le_bytes()); // e_entry
+        out.extend_from_slice(&0u64.to_le_bytes()); // e_phoff
+        out.extend_from_slice(&(shoff as u64).to_le_bytes()); // e_shoff
+        out.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+        out.extend_from_slice(&(ehdr_len as u16).to_le_bytes()); // e_ehsize
+        out.extend_from_slice(&0u16.to_le_bytes()); // e_phentsize
+        out.extend_from_slice(&0u16.to_le_bytes()); // e_phnum
+        out.extend_from_slice(&(shdr_len as u16).to_le_bytes()); // e_shentsize
+        out.extend_from_slice(&3u16.to_le_bytes()); // e_shnum
+        out.extend_from_slice(&2u16.to_le_bytes()); // e_shstrndx
+        assert_eq!(out.len(), ehdr_len);
+
+        out.extend_from_slice(payload);
+        out.extend_from_slice(shstrtab);
+        assert_eq!(out.len(), shoff);
+
+        // Section 0: SHN_UNDEF, all zero.
+        out.extend_from_slice(&[0u8; 64]);
+        // Section 1: ".dep-v0"
+        out.extend_from_slice(&1u32.to_le_bytes()); // sh_name
+        out.extend_from_slice(&1u32.to_le_bytes()); // sh_type (SHT_PROGBITS)
+        out.extend_from_slice(&0u64.to_le_bytes()); // sh_flags
+        out.extend_from_slice(&0u64.to_le_bytes()); // sh_addr
+        out.extend_from_slice(&(payload_offset as u64).to_le_bytes()); // sh_offset
+        out.extend_from_slice(&(payload.len() as u64).to_le_bytes()); // sh_size
+        out.extend_from_slice(&0u32.to_le_bytes()); // sh_link
+        out.extend_from_slice(&0u32.to_le_bytes()); // sh_info
+        out.extend_from_slice(&1u64.to_le_bytes()); // sh_addralign
+        out.extend_from_slice(&0u64.to_le_bytes()); // sh_entsize
+        // Section 2: ".shstrtab"
+        out.extend_from_slice(&9u32.to_le_bytes()); // sh_name
+        out.extend_from_slice(&3u32.to_le_bytes()); // sh_type (SHT_STRTAB)
+        out.extend_from_slice(&0u64.to_le_bytes()); // sh_flags
+        out.extend_from_slice(&0u64.to_le_bytes()); // sh_addr
+        out.extend_from_slice(&(shstrtab_offset as u64).to_le_bytes()); // sh_offset
+        out.extend_from_slice(&(shstrtab.len() as u64).to_le_bytes()); // sh_size
+        out.extend_from_slice(&0u32.to_le_bytes()); // sh_link
+        out.extend_from_slice(&0u32.to_le_bytes()); // sh_info
+        out.extend_from_slice(&1u64.to_le_bytes()); // sh_addralign
+        out.extend_from_slice(&0u64.to_le_bytes()); // sh_entsize
+
+        out
+    }
+
+    #[test]
+    fn round_trip_ar_archive() {
+        let payload = b"hello from an ar member";
+        let object = build_elf_with_dep_section(payload);
+
+        let mut data = AR_MAGIC.to_vec();
+        data.extend_from_slice(&member_header(b"audit.o/", object.len()));
+        data.extend_from_slice(&object);
+        if object.len() % 2 != 0 {
+            data.push(b'\n');
+        }
+
+        let (compression, found) = section_with_name(&data).unwrap();
+        assert_eq!(compression, Compression::Zlib);
+        assert_eq!(found, payload);
+    }
+
+    #[test]
+    fn symbol_table_member_is_skipped() {
+        // A lone, unindexed symbol-table member ("/") should never be handed to
+        // `raw_auditable_data` as if it were an object file.
+        let mut data = AR_MAGIC.to_vec();
+        let symtab_contents = b"not an object file";
+        data.extend_from_slice(&member_header(b"/ ", symtab_contents.len()));
+        data.extend_from_slice(symtab_contents);
+
+        assert!(matches!(section_with_name(&data), Err(Error::NoAuditData)));
+    }
+
+    #[test]
+    fn truncated_header_is_rejected() {
+        // Magic present, but not even one full 60-byte header follows.
+        let mut data = AR_MAGIC.to_vec();
+        data.extend_from_slice(b"short");
+        assert!(matches!(section_with_name(&data), Err(Error::NoAuditData)));
+    }
+
+    #[test]
+    fn truncated_member_is_rejected() {
+        // A well-formed header claims a member far larger than the bytes that follow.
+        let mut data = AR_MAGIC.to_vec();
+        data.extend_from_slice(&member_header(b"audit.o/", 1_000_000));
+        data.extend_from_slice(b"way too short");
+
+        assert!(matches!(
+            section_with_name(&data),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+}
@@ -5,7 +5,8 @@
 //!
 //! This crate parses platform-specific binary formats ([ELF](https://en.wikipedia.org/wiki/Executable_and_Linkable_Format),
 //! [PE](https://en.wikipedia.org/wiki/Portable_Executable),
-//! [Mach-O](https://en.wikipedia.org/wiki/Mach-O), [WASM](https://en.wikipedia.org/wiki/WebAssembly)) and obtains the compressed audit data.
+//! [Mach-O](https://en.wikipedia.org/wiki/Mach-O), [WASM](https://en.wikipedia.org/wiki/WebAssembly)), including
+//! fat Mach-O binaries and `ar` static library archives containing any of the above, and obtains the compressed audit data.
 //!
 //! Unlike other binary parsing crates, it is specifically designed to be resilient to malicious input.
 //! It 100% safe Rust and performs no heap allocations.
@@ -30,8 +31,9 @@
 //!     let mut input_binary = Vec::new();
 //!     f.read_to_end(&mut input_binary)?;
 //!     // Extract the compressed audit data
-//!     let compressed_audit_data = auditable_extract::raw_auditable_data(&input_binary)?;
+//!     let (compression, compressed_audit_data) = auditable_extract::raw_auditable_data(&input_binary)?;
 //!     // Decompress it with your Zlib implementation of choice. We recommend miniz_oxide
+//!     assert_eq!(compression, auditable_extract::Compression::Zlib);
 //!     use miniz_oxide::inflate::decompress_to_vec_zlib;
 //!     let decompressed_data = decompress_to_vec_zlib(&compressed_audit_data)
 //!         .map_err(|_| "Failed to decompress audit data")?;
@@ -43,45 +45,90 @@
 //! }
 //! ```
 
+mod ar;
+mod macho_fat;
 #[cfg(feature = "wasm")]
 mod wasm;
 
 use binfarce::Format;
+use std::ops::Range;
 
-/// Extracts the Zlib-compressed dependency info from an executable.
+/// The two section names the format can use, in lookup order: the versioned `.dep-v1`
+/// (which also carries a [`Compression`] tag) and the original `.dep-v0` (always Zlib,
+/// with no tag). This crate looks for `.dep-v1` first so that once something does write
+/// it, it takes precedence - but nothing in this repository writes `.dep-v1` yet; every
+/// binary `cargo auditable` currently produces still only has `.dep-v0`. This is
+/// forward-compatible read support, not a negotiated write path.
+const SECTION_NAME_V1: &str = ".dep-v1";
+const SECTION_NAME_V0: &str = ".dep-v0";
+
+/// The codec the dependency list bytes returned by [`raw_auditable_data`] were compressed with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    Zlib,
+    Zstd,
+}
+
+impl Compression {
+    fn from_tag(tag: u8) -> Result<Self, Error> {
+        match tag {
+            0 => Ok(Compression::Zlib),
+            1 => Ok(Compression::Zstd),
+            _ => Err(Error::UnknownCompressionCodec),
+        }
+    }
+}
+
+/// Extracts the compressed dependency info from an executable, along with the codec
+/// it was compressed with.
 ///
 /// This function does not allocate any memory on the heap and can be safely given untrusted input.
-pub fn raw_auditable_data(data: &[u8]) -> Result<&[u8], Error> {
+/// It only reads and bounds-checks the section contents - it never decompresses anything itself,
+/// so a malicious `.dep-v1`/`.dep-v0` payload can't cause a decompression bomb inside this crate.
+pub fn raw_auditable_data(data: &[u8]) -> Result<(Compression, &[u8]), Error> {
     match binfarce::detect_format(data) {
         Format::Elf32 { byte_order } => {
-            let section = binfarce::elf32::parse(data, byte_order)?
-                .section_with_name(".dep-v0")?
-                .ok_or(Error::NoAuditData)?;
-            Ok(data.get(section.range()?).ok_or(Error::UnexpectedEof)?)
+            let parsed = binfarce::elf32::parse(data, byte_order)?;
+            let v1 = parsed.section_with_name(SECTION_NAME_V1)?;
+            let v0 = parsed.section_with_name(SECTION_NAME_V0)?;
+            versioned_section(data, v1.map(|s| s.range()).transpose()?, v0.map(|s| s.range()).transpose()?)
         }
         Format::Elf64 { byte_order } => {
-            let section = binfarce::elf64::parse(data, byte_order)?
-                .section_with_name(".dep-v0")?
-                .ok_or(Error::NoAuditData)?;
-            Ok(data.get(section.range()?).ok_or(Error::UnexpectedEof)?)
+            let parsed = binfarce::elf64::parse(data, byte_order)?;
+            let v1 = parsed.section_with_name(SECTION_NAME_V1)?;
+            let v0 = parsed.section_with_name(SECTION_NAME_V0)?;
+            versioned_section(data, v1.map(|s| s.range()).transpose()?, v0.map(|s| s.range()).transpose()?)
         }
         Format::Macho => {
             let parsed = binfarce::macho::parse(data)?;
-            let section = parsed.section_with_name("__DATA", ".dep-v0")?;
-            let section = section.ok_or(Error::NoAuditData)?;
-            Ok(data.get(section.range()?).ok_or(Error::UnexpectedEof)?)
+            let v1 = parsed.section_with_name("__DATA", SECTION_NAME_V1)?;
+            let v0 = parsed.section_with_name("__DATA", SECTION_NAME_V0)?;
+            versioned_section(data, v1.map(|s| s.range()).transpose()?, v0.map(|s| s.range()).transpose()?)
         }
         Format::PE => {
             let parsed = binfarce::pe::parse(data)?;
-            let section = parsed
-                .section_with_name(".dep-v0")?
-                .ok_or(Error::NoAuditData)?;
-            Ok(data.get(section.range()?).ok_or(Error::UnexpectedEof)?)
+            let v1 = parsed.section_with_name(SECTION_NAME_V1)?;
+            let v0 = parsed.section_with_name(SECTION_NAME_V0)?;
+            versioned_section(data, v1.map(|s| s.range()).transpose()?, v0.map(|s| s.range()).transpose()?)
         }
         Format::Unknown => {
+            // `binfarce::detect_format` only recognizes thin (single-architecture)
+            // Mach-O files, so fat/universal binaries - the ones `lipo` and `cargo build`
+            // produce for macOS/iOS - show up here and need to be handled separately.
+            if macho_fat::is_fat_macho(data) {
+                return macho_fat::section_with_name(data, "__DATA");
+            }
+
+            // `staticlib` artifacts are `ar` archives of object files rather than
+            // a single linked executable, so the audit data lives in one of their members.
+            if ar::is_ar_archive(data) {
+                return ar::section_with_name(data);
+            }
+
             #[cfg(feature = "wasm")]
             if data.starts_with(b"\0asm") {
-                return wasm::raw_auditable_data_wasm(data);
+                // The wasm reader predates `.dep-v1` and only ever produces Zlib data.
+                return wasm::raw_auditable_data_wasm(data).map(|payload| (Compression::Zlib, payload));
             }
 
             Err(Error::NotAnExecutable)
@@ -89,6 +136,26 @@ pub fn raw_auditable_data(data: &[u8]) -> Result<&[u8], Error> {
     }
 }
 
+/// Shared by every format's branch of [`raw_auditable_data`]: given the already-looked-up
+/// byte ranges of the `.dep-v1` and `.dep-v0` sections (if present), prefers `.dep-v1`,
+/// splits off its codec tag, and falls back to treating `.dep-v0` as Zlib-compressed.
+pub(crate) fn versioned_section(
+    data: &[u8],
+    v1_range: Option<Range<usize>>,
+    v0_range: Option<Range<usize>>,
+) -> Result<(Compression, &[u8]), Error> {
+    if let Some(range) = v1_range {
+        let section = data.get(range).ok_or(Error::UnexpectedEof)?;
+        let (tag, payload) = section.split_first().ok_or(Error::UnexpectedEof)?;
+        return Ok((Compression::from_tag(*tag)?, payload));
+    }
+    if let Some(range) = v0_range {
+        let section = data.get(range).ok_or(Error::UnexpectedEof)?;
+        return Ok((Compression::Zlib, section));
+    }
+    Err(Error::NoAuditData)
+}
+
 #[cfg(all(fuzzing, feature = "wasm"))]
 pub fn raw_auditable_data_wasm_for_fuzz(input: &[u8]) -> Result<&[u8], Error> {
     wasm::raw_auditable_data_wasm(input)
@@ -103,6 +170,7 @@ pub enum Error {
     SymbolsSectionIsMissing,
     SectionIsMissing,
     UnexpectedSectionType,
+    UnknownCompressionCodec,
 }
 
 impl std::error::Error for Error {}
@@ -117,6 +185,7 @@ impl std::fmt::Display for Error {
             Error::SymbolsSectionIsMissing => "Symbols section missing from executable",
             Error::SectionIsMissing => "Section is missing from executable",
             Error::UnexpectedSectionType => "Unexpected executable section type",
+            Error::UnknownCompressionCodec => "Unknown compression codec tag in .dep-v1 section",
         };
         write!(f, "{message}")
     }
@@ -133,3 +202,75 @@ impl From<binfarce::ParseError> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v1_takes_precedence_over_v0() {
+        // bytes 0..3 are the .dep-v1 range (tag + payload), the rest is the .dep-v0 range.
+        let data = [1u8, b'v', b'1', b'-', b'p', b'a', b'y', b'l', b'o', b'a', b'd'];
+        let v1_range = 0..3; // tag (Zstd) + "v1"
+        let v0_range = 3..data.len(); // "-payload"
+
+        let (compression, payload) =
+            versioned_section(&data, Some(v1_range), Some(v0_range)).unwrap();
+        assert_eq!(compression, Compression::Zstd);
+        assert_eq!(payload, b"v1");
+    }
+
+    #[test]
+    fn falls_back_to_v0_when_v1_is_absent() {
+        let data = b"zlib bytes, no tag".to_vec();
+        let (compression, payload) = versioned_section(&data, None, Some(0..data.len())).unwrap();
+        assert_eq!(compression, Compression::Zlib);
+        assert_eq!(payload, data.as_slice());
+    }
+
+    #[test]
+    fn neither_section_present_is_no_audit_data() {
+        let data = b"nothing here".to_vec();
+        assert!(matches!(
+            versioned_section(&data, None, None),
+            Err(Error::NoAuditData)
+        ));
+    }
+
+    #[test]
+    fn unknown_codec_tag_is_rejected() {
+        let data = [42u8, b'x'];
+        assert!(matches!(
+            versioned_section(&data, Some(0..data.len()), None),
+            Err(Error::UnknownCompressionCodec)
+        ));
+    }
+
+    #[test]
+    fn empty_v1_section_is_unexpected_eof() {
+        // A zero-length .dep-v1 range has no byte to split off as the codec tag.
+        let data = b"irrelevant".to_vec();
+        assert!(matches!(
+            versioned_section(&data, Some(0..0), None),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn v1_range_past_end_of_data_is_unexpected_eof() {
+        let data = b"short".to_vec();
+        assert!(matches!(
+            versioned_section(&data, Some(0..1_000), None),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn v0_range_past_end_of_data_is_unexpected_eof() {
+        let data = b"short".to_vec();
+        assert!(matches!(
+            versioned_section(&data, None, Some(0..1_000)),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+//! Parses fat (a.k.a. "universal") Mach-O binaries: a thin container that bundles
+//! one Mach-O slice per architecture, as produced by `lipo` and by `cargo build`
+//! on macOS/iOS targets when cross-compiling for multiple architectures.
+//!
+//! The fat header and its `fat_arch` entries are always big-endian,
+//! regardless of the byte order of the Mach-O slices they describe.
+
+use crate::{versioned_section, Compression, Error};
+
+const FAT_MAGIC: [u8; 4] = [0xca, 0xfe, 0xba, 0xbe];
+const FAT_MAGIC_64: [u8; 4] = [0xca, 0xfe, 0xba, 0xbf];
+
+// `fat_header.nfat_arch` is a u32, but no real binary has anywhere near this many slices.
+// Capping it defends against a crafted header claiming billions of entries
+// and running far past the end of the (bounds-checked) input on each read.
+const MAX_FAT_ARCHES: u32 = 1024;
+
+/// Returns `true` if `data` starts with a 32-bit or 64-bit fat Mach-O magic number.
+pub fn is_fat_macho(data: &[u8]) -> bool {
+    data.starts_with(&FAT_MAGIC) || data.starts_with(&FAT_MAGIC_64)
+}
+
+/// Walks the slices of a fat Mach-O file and returns the versioned dependency section
+/// contents from the first slice that has one.
+pub fn section_with_name<'a>(data: &'a [u8], segment: &str) -> Result<(Compression, &'a [u8]), Error> {
+    let is_64 = data.starts_with(&FAT_MAGIC_64);
+    let nfat_arch = u32::from_be_bytes(
+        data.get(4..8)
+            .ok_or(Error::UnexpectedEof)?
+            .try_into()
+            .unwrap(),
+    );
+    let nfat_arch = nfat_arch.min(MAX_FAT_ARCHES) as usize;
+
+    let entry_size = if is_64 { 32 } else { 20 };
+    let mut offset = 8;
+    for _ in 0..nfat_arch {
+        let entry = data
+            .get(offset..offset + entry_size)
+            .ok_or(Error::UnexpectedEof)?;
+        let (slice_offset, slice_size) = if is_64 {
+            let offset = u64::from_be_bytes(entry[8..16].try_into().unwrap());
+            let size = u64::from_be_bytes(entry[16..24].try_into().unwrap());
+            (offset as usize, size as usize)
+        } else {
+            let offset = u32::from_be_bytes(entry[8..12].try_into().unwrap());
+            let size = u32::from_be_bytes(entry[12..16].try_into().unwrap());
+            (offset as usize, size as usize)
+        };
+
+        let slice_end = slice_offset.checked_add(slice_size).ok_or(Error::UnexpectedEof)?;
+        let slice = data.get(slice_offset..slice_end).ok_or(Error::UnexpectedEof)?;
+
+        if let Ok(parsed) = binfarce::macho::parse(slice) {
+            let v1 = parsed.section_with_name(segment, ".dep-v1").ok().flatten();
+            let v0 = parsed.section_with_name(segment, ".dep-v0").ok().flatten();
+            if v1.is_some() || v0.is_some() {
+                let v1_range = v1.and_then(|s| s.range().ok());
+                let v0_range = v0.and_then(|s| s.range().ok());
+                if let Ok(found) = versioned_section(slice, v1_range, v0_range) {
+                    return Ok(found);
+                }
+            }
+        }
+
+        offset += entry_size;
+    }
+    Err(Error::NoAuditData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a minimal single-architecture 64-bit Mach-O with one `__DATA,.dep-v0`
+    /// section holding `payload`.
+    fn build_thin_macho(payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        let header_len = 32;
+        let segment_cmd_len = 72;
+        let section_len = 80;
+        let payload_offset = header_len + segment_cmd_len + section_len;
+
+        // mach_header_64
+        out.extend_from_slice(&0xfeedfacfu32.to_le_bytes()); // magic (MH_MAGIC_64)
+        out.extend_from_slice(&0x0100_0007i32.to_le_bytes()); // cputype (CPU_TYPE_X86_64)
+        out.extend_from_slice(&3i32.to_le_bytes()); // cpusubtype
+        out.extend_from_slice(&2u32.to_le_bytes()); // filetype (MH_EXECUTE)
+        out.extend_from_slice(&1u32.to_le_bytes()); // ncmds
+        out.extend_from_slice(&((segment_cmd_len + section_len) as u32).to_le_bytes()); // sizeofcmds
+        out.extend_from_slice(&0u32.to_le_bytes()); // flags
+        out.extend_from_slice(&0u32.to_le_bytes()); // reserved
+
+        // segment_command_64 (LC_SEGMENT_64)
+        out.extend_from_slice(&0x19u32.to_le_bytes()); // cmd
+        out.extend_from_slice(&((segment_cmd_len + section_len) as u32).to_le_bytes()); // cmdsize
+        let mut segname = [0u8; 16];
+        segname[..6].copy_from_slice(b"__DATA");
+        out.extend_from_slice(&segname);
+        out.extend_from_slice(&0u64.to_le_bytes()); // vmaddr
+        out.extend_from_slice(&0u64.to_le_bytes()); // vmsize
+        out.extend_from_slice(&0u64.to_le_bytes()); // fileoff
+        out.extend_from_slice(&0u64.to_le_bytes()); // filesize
+        out.extend_from_slice(&0i32.to_le_bytes()); // maxprot
+        out.extend_from_slice(&0i32.to_le_bytes()); // initprot
+        out.extend_from_slice(&1u32.to_le_bytes()); // nsects
+        out.extend_from_slice(&0u32.to_le_bytes()); // flags
+
+        // section_64
+        let mut sectname = [0u8; 16];
+        sectname[..7].copy_from_slice(b".dep-v0");
+        out.extend_from_slice(&sectname);
+        out.extend_from_slice(&segname);
+        out.extend_from_slice(&0u64.to_le_bytes()); // addr
+        out.extend_from_slice(&(payload.len() as u64).to_le_bytes()); // size
+        out.extend_from_slice(&(payload_offset as u32).to_le_bytes()); // offset
+        out.extend_from_slice(&0u32.to_le_bytes()); // align
+        out.extend_from_slice(&0u32.to_le_bytes()); // reloff
+        out.extend_from_slice(&0u32.to_le_bytes()); // nreloc
+        out.extend_from_slice(&0u32.to_le_bytes()); // flags
+        out.extend_from_slice(&0u32.to_le_bytes()); // reserved1
+        out.extend_from_slice(&0u32.to_le_bytes()); // reserved2
+        out.extend_from_slice(&0u32.to_le_bytes()); // reserved3
+
+        assert_eq!(out.len(), payload_offset);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// Wraps `thin` as the single slice of a 32-bit fat Mach-O.
+    fn build_fat_macho(thin: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let slice_offset = 8 + 20; // fat_header + one fat_arch entry
+
+        out.extend_from_slice(&FAT_MAGIC);
+        out.extend_from_slice(&1u32.to_be_bytes()); // nfat_arch
+
+        out.extend_from_slice(&0x0100_0007i32.to_be_bytes()); // cputype
+        out.extend_from_slice(&3i32.to_be_bytes()); // cpusubtype
+        out.extend_from_slice(&(slice_offset as u32).to_be_bytes()); // offset
+        out.extend_from_slice(&(thin.len() as u32).to_be_bytes()); // size
+        out.extend_from_slice(&0u32.to_be_bytes()); // align
+
+        assert_eq!(out.len(), slice_offset);
+        out.extend_from_slice(thin);
+        out
+    }
+
+    #[test]
+    fn round_trip_fat_macho() {
+        let payload = b"hello from a fat macho slice";
+        let thin = build_thin_macho(payload);
+        let fat = build_fat_macho(&thin);
+
+        let (compression, found) = section_with_name(&fat, "__DATA").unwrap();
+        assert_eq!(compression, Compression::Zlib);
+        assert_eq!(found, payload);
+    }
+
+    #[test]
+    fn truncated_header_is_rejected() {
+        // Only the magic, no room for `nfat_arch`.
+        let data = FAT_MAGIC.to_vec();
+        assert!(matches!(
+            section_with_name(&data, "__DATA"),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn oversized_nfat_arch_is_rejected() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&FAT_MAGIC);
+        data.extend_from_slice(&u32::MAX.to_be_bytes()); // nfat_arch: absurdly large
+        // No `fat_arch` entries actually follow, so even after capping to
+        // `MAX_FAT_ARCHES` the very first entry read should fail cleanly.
+        assert!(matches!(
+            section_with_name(&data, "__DATA"),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn fat_arch_64_offset_size_overflow_is_rejected() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&FAT_MAGIC_64);
+        data.extend_from_slice(&1u32.to_be_bytes()); // nfat_arch
+
+        // A single `fat_arch_64` entry whose offset + size overflows `usize`
+        // even on a 64-bit host.
+        data.extend_from_slice(&0i32.to_be_bytes()); // cputype
+        data.extend_from_slice(&0i32.to_be_bytes()); // cpusubtype
+        data.extend_from_slice(&u64::MAX.to_be_bytes()); // offset
+        data.extend_from_slice(&u64::MAX.to_be_bytes()); // size
+        data.extend_from_slice(&0u32.to_be_bytes()); // align
+        data.extend_from_slice(&0u32.to_be_bytes()); // reserved
+
+        assert!(matches!(
+            section_with_name(&data, "__DATA"),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+}
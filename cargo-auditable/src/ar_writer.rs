@@ -0,0 +1,229 @@
+//! Appends a single object-file member to an existing Unix `ar` archive.
+//!
+//! `staticlib` artifacts never go through a final Rust link step, so there's no
+//! `-Clink-arg` we can hook to get our metadata object linked in like we do for
+//! `bin`/`cdylib`. Instead, once rustc has produced the `.a` archive, we open it
+//! back up and append the metadata object as a plain member.
+//!
+//! Note that some non-Rust linkers select archive members purely from the archive's
+//! `/` symbol-table index, not by member name or by `-u`/`--undefined` flags passed to
+//! *this* link (those flags only affect symbols the linker is still looking for when it
+//! reaches our archive; they don't make it scan members the index doesn't mention). An
+//! appended-but-unindexed member is therefore invisible to any such linker. Rather than
+//! reimplement the System V/GNU symbol table format by hand, we let the platform `ar`
+//! rebuild it for us after appending.
+
+use std::{env, ffi::OsString, fs, io, path::Path, process::Command};
+
+const AR_MAGIC: &[u8] = b"!<arch>\n";
+const HEADER_LEN: usize = 60;
+const MEMBER_NAME: &[u8] = b"audit.o/";
+
+/// Appends `object_path`'s contents as a new member of the `ar` archive at `archive_path`,
+/// then rebuilds the archive's symbol table so the member is actually reachable by a link.
+pub fn insert_object_member(
+    archive_path: &Path,
+    object_path: &Path,
+    target_triple: &str,
+) -> io::Result<()> {
+    let mut archive = fs::read(archive_path)?;
+    let object = fs::read(object_path)?;
+
+    if !archive.starts_with(AR_MAGIC) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not an ar archive", archive_path.display()),
+        ));
+    }
+
+    archive.extend_from_slice(&member_header(object.len()));
+    archive.extend_from_slice(&object);
+    if object.len() % 2 != 0 {
+        // Members are padded to an even length.
+        archive.push(b'\n');
+    }
+
+    fs::write(archive_path, archive)?;
+
+    reindex_symbol_table(archive_path, target_triple)
+}
+
+/// Rebuilds the archive's `/` symbol-table member via `ar s`, the same operation `ranlib`
+/// performs, so `AUDITABLE_VERSION_INFO` in the member we just appended is indexed like
+/// any other archive member instead of only being reachable by name.
+fn reindex_symbol_table(archive_path: &Path, target_triple: &str) -> io::Result<()> {
+    let archiver = archiver_command(target_triple);
+    let status = Command::new(&archiver).arg("s").arg(archive_path).status()?;
+    if !status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "`{} s {}` exited with {status}",
+                archiver.to_string_lossy(),
+                archive_path.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Picks the archiver to invoke, honoring the same environment variables the `cc` crate
+/// (and thus most `-sys` crates doing C/C++ interop) respects for cross-compilation:
+/// a target-specific `AR_<target_with_underscores>` override first, then the generic `AR`,
+/// falling back to plain `ar` on `$PATH`. Without this, cross builds using a non-default
+/// archiver - or a plain MSVC install, which has no `ar` at all - would always fail here
+/// even though the user already told the rest of the toolchain which archiver to use.
+fn archiver_command(target_triple: &str) -> OsString {
+    let target_specific_var = format!("AR_{}", target_triple.replace(['-', '.'], "_"));
+    env::var_os(target_specific_var)
+        .or_else(|| env::var_os("AR"))
+        .unwrap_or_else(|| OsString::from("ar"))
+}
+
+/// Builds a 60-byte `ar` member header for an object of the given size.
+/// Everything but the name and size is left at harmless defaults, matching
+/// what `ar` itself writes for reproducible builds (mtime/uid/gid of zero).
+fn member_header(size: usize) -> [u8; HEADER_LEN] {
+    let mut header = [b' '; HEADER_LEN];
+    write_field(&mut header[0..16], MEMBER_NAME);
+    write_field(&mut header[16..28], b"0"); // mtime
+    write_field(&mut header[28..34], b"0"); // uid
+    write_field(&mut header[34..40], b"0"); // gid
+    write_field(&mut header[40..48], b"100644"); // mode (octal)
+    write_field(&mut header[48..58], size.to_string().as_bytes());
+    header[58] = b'`';
+    header[59] = b'\n';
+    header
+}
+
+fn write_field(field: &mut [u8], value: &[u8]) {
+    field[..value.len()].copy_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // `archiver_command` reads process-wide environment variables, so tests that set
+    // them must not run concurrently with each other.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn member_header_has_expected_layout() {
+        let header = member_header(1234);
+        assert_eq!(&header[0..8], MEMBER_NAME);
+        assert_eq!(&header[8..16], b"        "); // name field padded with spaces
+        assert_eq!(&header[16..28], b"0           "); // mtime
+        assert_eq!(&header[28..34], b"0     "); // uid
+        assert_eq!(&header[34..40], b"0     "); // gid
+        assert_eq!(&header[40..48], b"100644  "); // mode
+        assert_eq!(&header[48..58], b"1234      "); // size
+        assert_eq!(header[58], b'`');
+        assert_eq!(header[59], b'\n');
+    }
+
+    #[test]
+    fn archiver_command_defaults_to_ar() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        env::remove_var("AR_x86_64_unknown_linux_gnu");
+        env::remove_var("AR");
+        assert_eq!(
+            archiver_command("x86_64-unknown-linux-gnu"),
+            OsString::from("ar")
+        );
+    }
+
+    #[test]
+    fn archiver_command_honors_generic_ar() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        env::remove_var("AR_x86_64_unknown_linux_gnu");
+        env::set_var("AR", "my-ar");
+        let result = archiver_command("x86_64-unknown-linux-gnu");
+        env::remove_var("AR");
+        assert_eq!(result, OsString::from("my-ar"));
+    }
+
+    #[test]
+    fn archiver_command_prefers_target_specific_ar_over_generic() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        env::set_var("AR", "generic-ar");
+        env::set_var("AR_x86_64_pc_windows_msvc", "llvm-ar");
+        let result = archiver_command("x86_64-pc-windows-msvc");
+        env::remove_var("AR");
+        env::remove_var("AR_x86_64_pc_windows_msvc");
+        assert_eq!(result, OsString::from("llvm-ar"));
+    }
+
+    #[test]
+    fn archiver_command_replaces_dashes_and_dots_in_target() {
+        let _guard = ENV_LOCK.lock().unwrap();
+        env::remove_var("AR");
+        env::set_var("AR_thumbv7m_none_eabi", "arm-none-eabi-ar");
+        let result = archiver_command("thumbv7m-none-eabi");
+        env::remove_var("AR_thumbv7m_none_eabi");
+        assert_eq!(result, OsString::from("arm-none-eabi-ar"));
+    }
+
+    #[test]
+    fn insert_object_member_rejects_non_archive() {
+        let dir = std::env::temp_dir().join(format!(
+            "cargo-auditable-ar-writer-test-{}-not-archive",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let archive_path = dir.join("not-an-archive.a");
+        let object_path = dir.join("audit.o");
+        fs::write(&archive_path, b"definitely not an ar archive").unwrap();
+        fs::write(&object_path, b"object contents").unwrap();
+
+        let result = insert_object_member(&archive_path, &object_path, "x86_64-unknown-linux-gnu");
+
+        fs::remove_dir_all(&dir).ok();
+        assert!(result.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn insert_object_member_appends_member_with_even_padding() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _guard = ENV_LOCK.lock().unwrap();
+        let dir = std::env::temp_dir().join(format!(
+            "cargo-auditable-ar-writer-test-{}-append",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+
+        // A fake `ar` that does nothing: this test only checks that the member got
+        // appended correctly, not that a real symbol table got rebuilt.
+        let fake_ar_path = dir.join("fake-ar.sh");
+        fs::write(&fake_ar_path, b"#!/bin/sh\nexit 0\n").unwrap();
+        fs::set_permissions(&fake_ar_path, fs::Permissions::from_mode(0o755)).unwrap();
+        env::set_var("AR", &fake_ar_path);
+
+        let archive_path = dir.join("libfoo.a");
+        let object_path = dir.join("foo_audit_data.o");
+        let object_contents = b"odd length payload!"; // 19 bytes: needs padding
+        fs::write(&archive_path, AR_MAGIC).unwrap();
+        fs::write(&object_path, object_contents).unwrap();
+
+        let result =
+            insert_object_member(&archive_path, &object_path, "x86_64-unknown-linux-gnu");
+        env::remove_var("AR");
+
+        assert!(result.is_ok(), "{result:?}");
+        let archive = fs::read(&archive_path).unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(archive.starts_with(AR_MAGIC));
+        let header = &archive[AR_MAGIC.len()..AR_MAGIC.len() + HEADER_LEN];
+        assert_eq!(&header[0..8], MEMBER_NAME);
+        let member_start = AR_MAGIC.len() + HEADER_LEN;
+        let member_end = member_start + object_contents.len();
+        assert_eq!(&archive[member_start..member_end], object_contents);
+        // Odd-length member is padded with a trailing newline.
+        assert_eq!(archive.len(), member_end + 1);
+        assert_eq!(archive[member_end], b'\n');
+    }
+}
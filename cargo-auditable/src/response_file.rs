@@ -0,0 +1,220 @@
+//! Expands rustc `@path` response-file arguments.
+//!
+//! `rustc` accepts arguments of the form `@path`, where `path` points to a file
+//! containing one argument per line, and `@shell:path`, where the file is instead
+//! one whitespace-separated, shell-quoted argument list (quoting rules below).
+//! Tools that wrap `rustc` (e.g. `sccache`, or Cargo itself on Windows with very
+//! long command lines) sometimes move most of the real arguments into such a file
+//! instead of passing them on the command line. If we don't expand these before
+//! scanning for `--crate-name` and `--crate-type`, we silently fail to detect -
+//! and thus fail to inject audit data into - real compilation commands.
+//!
+//! See <https://github.com/rust-secure-code/cargo-auditable/issues/87>.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+
+/// Maximum depth of `@file` nesting we'll follow, to guard against
+/// a response file (directly or transitively) referencing itself.
+const MAX_RECURSION_DEPTH: u32 = 16;
+
+/// Expands any `@path` arguments in `args` into the arguments they contain,
+/// recursively. Arguments that don't start with `@`, or that do but don't
+/// point to a file we can read, are passed through unchanged.
+pub fn expand_args(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let mut expanded = Vec::new();
+    for arg in args {
+        expand_arg(&arg, 0, &mut expanded);
+    }
+    expanded
+}
+
+fn expand_arg(arg: &OsString, depth: u32, out: &mut Vec<OsString>) {
+    if depth >= MAX_RECURSION_DEPTH {
+        out.push(arg.clone());
+        return;
+    }
+    let Some(arg_str) = arg.to_str() else {
+        // Non-UTF8 arguments can't be `@file` references (rustc itself
+        // requires response file paths to be valid UTF-8), pass through as-is.
+        out.push(arg.clone());
+        return;
+    };
+    let Some(path) = arg_str.strip_prefix('@') else {
+        out.push(arg.clone());
+        return;
+    };
+
+    // `@shell:path` is whitespace-separated and shell-quoted; plain `@path` is
+    // one literal argument per line, with no quote processing at all.
+    if let Some(path) = path.strip_prefix("shell:") {
+        match read_response_file(Path::new(path)).and_then(|contents| split_shell_words(&contents)) {
+            Some(words) => {
+                for word in words {
+                    expand_arg(&OsString::from(word), depth + 1, out);
+                }
+            }
+            // Either the file couldn't be opened, or its quoting was malformed
+            // (e.g. an unterminated quote) - in both cases pass the arg through
+            // unchanged rather than erroring.
+            None => out.push(arg.clone()),
+        }
+        return;
+    }
+
+    match read_response_file(Path::new(path)) {
+        Some(contents) => {
+            for line in contents.lines() {
+                expand_arg(&OsString::from(line), depth + 1, out);
+            }
+        }
+        // Not a path we could open - could be a literal value that happens
+        // to start with '@' - so pass it through unchanged rather than erroring.
+        None => out.push(arg.clone()),
+    }
+}
+
+fn read_response_file(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+/// Splits `input` into words the way `@shell:path` response files are quoted:
+/// whitespace separates words, single quotes take everything up to the next
+/// single quote literally, and double quotes take everything up to the next
+/// double quote literally except for a backslash escaping `"`, `\`, `$` or `` ` ``.
+/// Returns `None` if a quote is left unterminated.
+fn split_shell_words(input: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return None, // unterminated quote
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return None, // unterminated quote
+                        },
+                        Some(c) => current.push(c),
+                        None => return None, // unterminated quote
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => current.push(c),
+                    None => return None, // trailing backslash, nothing to escape
+                }
+            }
+            c => {
+                in_word = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_whitespace_separated_words() {
+        assert_eq!(
+            split_shell_words("--crate-name foo --edition 2021").unwrap(),
+            vec!["--crate-name", "foo", "--edition", "2021"]
+        );
+    }
+
+    #[test]
+    fn single_quotes_are_fully_literal() {
+        // Nothing is special inside single quotes, not even a backslash.
+        assert_eq!(
+            split_shell_words(r#"'a\b $c "d"'"#).unwrap(),
+            vec![r#"a\b $c "d""#]
+        );
+    }
+
+    #[test]
+    fn double_quotes_only_escape_a_few_characters() {
+        // `\"`, `\\`, `\$` and `` \` `` drop the backslash; any other escaped
+        // character keeps its backslash, since double quotes don't treat it as special.
+        assert_eq!(
+            split_shell_words(r#""a\"b\\c\$d\`e\nf""#).unwrap(),
+            vec![r#"a"b\c$d`e\nf"#]
+        );
+    }
+
+    #[test]
+    fn bare_backslash_escapes_the_next_character() {
+        assert_eq!(split_shell_words(r"a\ b c").unwrap(), vec!["a b", "c"]);
+    }
+
+    #[test]
+    fn adjacent_quoted_and_unquoted_runs_join_into_one_word() {
+        // `'it'\''s'` is the standard shell idiom for an embedded single quote:
+        // "it" (quoted) + "'" (escaped) + "s" (quoted), all one word.
+        assert_eq!(split_shell_words(r"'it'\''s'").unwrap(), vec!["it's"]);
+    }
+
+    #[test]
+    fn unterminated_single_quote_is_rejected() {
+        assert_eq!(split_shell_words("'unterminated"), None);
+    }
+
+    #[test]
+    fn unterminated_double_quote_is_rejected() {
+        assert_eq!(split_shell_words("\"unterminated"), None);
+    }
+
+    #[test]
+    fn unterminated_escape_inside_double_quotes_is_rejected() {
+        assert_eq!(split_shell_words("\"a\\"), None);
+    }
+
+    #[test]
+    fn trailing_bare_backslash_is_rejected() {
+        assert_eq!(split_shell_words(r"a\"), None);
+    }
+
+    #[test]
+    fn empty_input_is_no_words() {
+        assert_eq!(split_shell_words("").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn whitespace_only_input_is_no_words() {
+        assert_eq!(split_shell_words("   \t\n  ").unwrap(), Vec::<String>::new());
+    }
+}
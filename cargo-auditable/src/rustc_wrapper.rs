@@ -1,62 +1,134 @@
 use std::{
     env,
     ffi::{OsStr, OsString},
+    path::PathBuf,
     process::Command,
 };
 
-use crate::{collect_audit_data, object_file, rustc_arguments, target_info};
+use crate::{
+    ar_writer, collect_audit_data, object_file, response_file, rustc_arguments, target_info,
+    wasm_postprocess,
+};
 
 use std::io::BufRead;
 
 pub fn main(rustc_path: &OsStr) {
     let mut command = rustc_command(rustc_path);
 
+    // If we're building a staticlib, the audit data gets injected into the archive
+    // after rustc has produced it, since there's no final link step to hook into.
+    // Populated below, once we know the crate name and output directory.
+    let mut staticlib_injection: Option<(PathBuf, PathBuf, String)> = None;
+    // Likewise for wasm32 targets: there's no object-file-plus-linker-flag trick to play,
+    // so the compressed payload is stashed here and spliced into the emitted module afterwards.
+    let mut wasm_injection: Option<(PathBuf, Vec<u8>)> = None;
+
+    // Response files (`@path` arguments) can hide the flags we're looking for
+    // behind a file rustc reads itself, so expand them before we look at anything.
+    // Note we only use the expanded arguments for our own detection logic below -
+    // rustc is still invoked with the original, unexpanded arguments, since the whole
+    // point of response files is to keep the actual command line short.
+    let wrapper_args: Vec<OsString> = response_file::expand_args(env::args_os().skip(2));
+
     // Binaries and C dynamic libraries are not built as non-primary packages,
     // so this should not cause issues with Cargo caches.
     if env::var_os("CARGO_PRIMARY_PACKAGE").is_some() {
-        let arg_parsing_result = rustc_arguments::parse_args();
-        if let Ok(args) = rustc_arguments::parse_args() {
-            // Only inject audit data into crate types 'bin' and 'cdylib'
-            if args.crate_types.contains(&"bin".to_owned())
-                || args.crate_types.contains(&"cdylib".to_owned())
-            {
+        let arg_parsing_result = rustc_arguments::parse_args(&wrapper_args);
+        if let Ok(args) = rustc_arguments::parse_args(&wrapper_args) {
+            let is_bin_or_cdylib = args.crate_types.contains(&"bin".to_owned())
+                || args.crate_types.contains(&"cdylib".to_owned());
+            let is_staticlib = args.crate_types.contains(&"staticlib".to_owned());
+            // Inject audit data into crate types 'bin', 'cdylib' and 'staticlib'
+            if is_bin_or_cdylib || is_staticlib {
                 // Get the audit data to embed
                 let target_triple = args
                     .target
                     .clone()
                     .unwrap_or_else(|| rustc_host_target_triple(rustc_path));
+                // `compressed_dependency_list` returns the final Zlib-compressed bytes,
+                // embedded verbatim under `.dep-v0`. There is no write-side support for
+                // the versioned `.dep-v1` format yet - see `auditable_extract::Compression`
+                // for the read side, which already understands both.
                 let contents: Vec<u8> =
                     collect_audit_data::compressed_dependency_list(&args, &target_triple);
-                // write the audit info to an object file
-                let target_info = target_info::rustc_target_info(rustc_path, &target_triple);
-                let binfile = object_file::create_metadata_file(
-                    &target_info,
-                    &target_triple,
-                    &contents,
-                    "AUDITABLE_VERSION_INFO",
-                );
-                if let Some(file) = binfile {
-                    // Place the audit data in the output dir.
-                    // We can place it anywhere really, the only concern is clutter and name collisions,
-                    // and the target dir is locked so we're probably good
-                    let filename = format!("{}_audit_data.o", args.crate_name);
-                    let path = args.out_dir.join(filename);
-                    std::fs::write(&path, file).expect("Unable to write output file");
-
-                    // Modify the rustc command to link the object file with audit data
-                    let mut linker_command = OsString::from("-Clink-arg=");
-                    linker_command.push(&path);
-                    command.arg(linker_command);
-                    // Prevent the symbol from being removed as unused by the linker
-                    if target_triple.contains("-apple-") {
-                        command.arg("-Clink-arg=-Wl,-u,_AUDITABLE_VERSION_INFO");
+
+                let is_wasm32 = target_triple.starts_with("wasm32-");
+
+                if is_bin_or_cdylib && is_wasm32 {
+                    // There's no ELF/PE/Mach-O object format to emit for wasm32, and `-Clink-arg`
+                    // doesn't carry over to `wasm-ld` the way it does for native linkers, so skip
+                    // the object-file route entirely and post-process the emitted module instead.
+                    // `contents` isn't needed by any other branch of this `if`/`else` chain once
+                    // we're here, since only one branch runs - move it instead of cloning.
+                    let wasm_path = args.out_dir.join(format!("{}.wasm", args.crate_name));
+                    wasm_injection = Some((wasm_path, contents));
+                } else if is_staticlib && is_wasm32 {
+                    // `object_file::create_metadata_file` returns `None` for every wasm32
+                    // target, so routing a wasm32 staticlib through the object-file route
+                    // below would always hit the "unsupported architecture" branch and print
+                    // a misleading "target is not supported" warning - wasm32 *is* supported,
+                    // just not yet for this particular crate type. Warn about that precisely
+                    // instead, rather than falling through into the native object-file route.
+                    eprintln!(
+                        "WARNING: 'cargo auditable' does not yet support embedding audit data \
+                        in a 'staticlib' built for a wasm32 target.\n\
+                        The build will continue, but no audit data will be injected into the archive."
+                    );
+                } else {
+                    // A non-wasm32 staticlib never goes through a link step, so it always needs
+                    // the object-file route to produce the member `ar_writer` splices in afterwards.
+                    // A non-wasm32 bin/cdylib also needs it, to actually link the object in.
+                    // (A wasm32 bin/cdylib was already handled above; a wasm32 staticlib isn't
+                    // supported yet, and was already warned about above.)
+                    // write the audit info to an object file
+                    let target_info = target_info::rustc_target_info(rustc_path, &target_triple);
+                    let binfile = object_file::create_metadata_file(
+                        &target_info,
+                        &target_triple,
+                        &contents,
+                        "AUDITABLE_VERSION_INFO",
+                    );
+                    if let Some(file) = binfile {
+                        // Place the audit data in the output dir.
+                        // We can place it anywhere really, the only concern is clutter and name collisions,
+                        // and the target dir is locked so we're probably good
+                        let filename = format!("{}_audit_data.o", args.crate_name);
+                        let path = args.out_dir.join(filename);
+                        std::fs::write(&path, file).expect("Unable to write output file");
+
+                        if is_bin_or_cdylib && !is_wasm32 {
+                            // Modify the rustc command to link the object file with audit data
+                            let mut linker_command = OsString::from("-Clink-arg=");
+                            linker_command.push(&path);
+                            command.arg(linker_command);
+                            // Prevent the symbol from being removed as unused by the linker
+                            if target_triple.contains("-apple-") {
+                                command.arg("-Clink-arg=-Wl,-u,_AUDITABLE_VERSION_INFO");
+                            } else {
+                                command.arg("-Clink-arg=-Wl,--undefined=AUDITABLE_VERSION_INFO");
+                            }
+                        }
+                        if is_staticlib {
+                            // There's no link step to hook for a staticlib, so remember where
+                            // the metadata object and the archive rustc is about to produce are,
+                            // and splice the former into the latter once rustc has run.
+                            // The filename isn't always `lib<crate_name>.a` - MSVC targets emit
+                            // `<crate_name>.lib` with no prefix - so ask rustc what it will
+                            // actually produce instead of assuming the Unix convention.
+                            let filename = rustc_staticlib_filename(
+                                rustc_path,
+                                &args.crate_name,
+                                &target_triple,
+                            );
+                            let archive_path = args.out_dir.join(filename);
+                            staticlib_injection =
+                                Some((archive_path, path, target_triple.clone()));
+                        }
                     } else {
-                        command.arg("-Clink-arg=-Wl,--undefined=AUDITABLE_VERSION_INFO");
+                        // create_metadata_file() returned None, indicating an unsupported architecture
+                        eprintln!("WARNING: target '{target_triple}' is not supported by 'cargo auditable'!\n\
+                        The build will continue, but no audit data will be injected into the binary.");
                     }
-                } else {
-                    // create_metadata_file() returned None, indicating an unsupported architecture
-                    eprintln!("WARNING: target '{target_triple}' is not supported by 'cargo auditable'!\n\
-                    The build will continue, but no audit data will be injected into the binary.");
                 }
             }
         } else {
@@ -73,8 +145,8 @@ pub fn main(rustc_path: &OsStr) {
             // so parsing them properly adds a lot of complexity.
             // So we just check if `--crate-name` is passed and if not,
             // assume that it's a non-compilation command.
-            if env::args_os()
-                .skip(2)
+            if wrapper_args
+                .iter()
                 .any(|arg| arg == OsStr::new("--crate-name"))
             {
                 // this was a compilation command, bail
@@ -88,10 +160,42 @@ pub fn main(rustc_path: &OsStr) {
     let results = command
         .status()
         .expect("Failed to invoke rustc! Make sure it's in your $PATH");
+
+    if results.success() {
+        if let Some((archive_path, object_path, target_triple)) = staticlib_injection {
+            if let Err(e) =
+                ar_writer::insert_object_member(&archive_path, &object_path, &target_triple)
+            {
+                eprintln!(
+                    "WARNING: failed to inject audit data into '{}': {e}\n\
+                    The build will continue, but no audit data will be embedded in the archive.",
+                    archive_path.display()
+                );
+            }
+        }
+        if let Some((wasm_path, contents)) = wasm_injection {
+            // `contents` is the same Zlib-compressed-with-no-tag bytes used for the native
+            // object-file path above, so it has to be embedded under `.dep-v0` specifically -
+            // there is no write-side support for the versioned `.dep-v1` format yet.
+            if let Err(e) = wasm_postprocess::append_custom_section(&wasm_path, ".dep-v0", &contents)
+            {
+                eprintln!(
+                    "WARNING: failed to inject audit data into '{}': {e}\n\
+                    The build will continue, but no audit data will be embedded in the module.",
+                    wasm_path.display()
+                );
+            }
+        }
+    }
+
     std::process::exit(results.code().unwrap());
 }
 
 /// Creates a rustc command line and populates arguments from arguments passed to us.
+///
+/// This uses the original, unexpanded arguments (including any `@file` response files) -
+/// rustc understands those natively, and re-expanding them here would defeat their purpose
+/// of keeping the command line short.
 fn rustc_command(rustc_path: &OsStr) -> Command {
     let mut command = Command::new(rustc_path);
     // Pass along all the arguments that Cargo meant to pass to rustc
@@ -101,6 +205,26 @@ fn rustc_command(rustc_path: &OsStr) -> Command {
     command
 }
 
+/// Asks rustc what filename it will give a `staticlib` built from `crate_name` for
+/// `target_triple`, instead of assuming the Unix `lib<crate_name>.a` convention - MSVC
+/// targets emit `<crate_name>.lib`, with no `lib` prefix and a different extension.
+fn rustc_staticlib_filename(rustc_path: &OsStr, crate_name: &str, target_triple: &str) -> String {
+    // rustc only prints file names when given an input; `-` reads the (empty) stdin.
+    let output = Command::new(rustc_path)
+        .arg("-")
+        .args(["--crate-type", "staticlib", "--crate-name", crate_name])
+        .args(["--target", target_triple])
+        .args(["--print", "file-names"])
+        .output()
+        .expect("Failed to invoke rustc! Is it in your $PATH?");
+    String::from_utf8(output.stdout)
+        .expect("rustc printed non-UTF8 output to --print file-names")
+        .lines()
+        .next()
+        .expect("rustc did not print a filename for --print file-names")
+        .to_owned()
+}
+
 /// Returns the default target triple for the rustc we're running
 fn rustc_host_target_triple(rustc_path: &OsStr) -> String {
     Command::new(rustc_path)
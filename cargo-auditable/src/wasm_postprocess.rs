@@ -0,0 +1,45 @@
+//! Appends the compressed audit data to a `.wasm` module as a custom section.
+//!
+//! `object_file::create_metadata_file` returns `None` for `wasm32-*` targets - there's no
+//! ELF/PE/Mach-O object to emit, and `-Clink-arg=-Wl,--undefined=...` doesn't mean anything
+//! to `wasm-ld` - so there's no link step to hook the way we do for native targets. Instead,
+//! once rustc has produced the `.wasm` module, we post-process it by appending a custom
+//! section named `.dep-v0` holding the same Zlib-compressed payload used everywhere else.
+//! This is what `auditable_extract::wasm::raw_auditable_data_wasm` reads back on the other end.
+
+use std::{fs, io, path::Path};
+
+/// Custom section id, per the WASM binary format.
+const CUSTOM_SECTION_ID: u8 = 0;
+
+/// Appends a custom section named `name` containing `payload` to the end of the wasm module at `wasm_path`.
+/// Custom sections are allowed to appear anywhere in a module, including after every other section,
+/// so appending is sufficient - no need to parse or rewrite the rest of the module.
+pub fn append_custom_section(wasm_path: &Path, name: &str, payload: &[u8]) -> io::Result<()> {
+    let mut module = fs::read(wasm_path)?;
+
+    let mut contents = Vec::with_capacity(name.len() + payload.len());
+    write_leb128_u32(&mut contents, name.len() as u32);
+    contents.extend_from_slice(name.as_bytes());
+    contents.extend_from_slice(payload);
+
+    module.push(CUSTOM_SECTION_ID);
+    write_leb128_u32(&mut module, contents.len() as u32);
+    module.extend_from_slice(&contents);
+
+    fs::write(wasm_path, module)
+}
+
+/// Encodes `value` as unsigned LEB128, the variable-length integer encoding used throughout the WASM format.
+fn write_leb128_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            break;
+        } else {
+            out.push(byte | 0x80);
+        }
+    }
+}